@@ -1,72 +1,551 @@
-use std::path::Path;
-use std::sync::RwLock;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use reqwest::{Client, Request};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
 
 use crate::authentication_manager::ServiceAccount;
 use crate::error::Error;
 use crate::types::Token;
 
+/// Cache key derived from the requested scope set.
+///
+/// Scopes are sorted and space-joined so that `["a", "b"]` and `["b", "a"]`
+/// resolve to the same entry.
+type ScopeKey = String;
+
+/// A cached token together with its absolute expiry, stored as a Unix
+/// timestamp so it can be persisted and compared after a restart.
+#[derive(Debug, Clone)]
+pub struct CachedToken {
+    token: Token,
+    expires_at: i64,
+}
+
+impl CachedToken {
+    fn new(token: Token, lifetime: Duration) -> Self {
+        Self {
+            token,
+            expires_at: Self::now() + lifetime.as_secs() as i64,
+        }
+    }
+
+    /// The on-disk form. Stores the bearer string and expiry only, so
+    /// persistence needs just `Token: Deserialize` (which the endpoint already
+    /// requires) rather than `Token: Serialize`.
+    fn to_stored(&self) -> StoredToken {
+        StoredToken {
+            access_token: self.token.as_str().to_string(),
+            expires_at: self.expires_at,
+        }
+    }
+
+    fn from_stored(stored: StoredToken) -> Result<Self, serde_json::Error> {
+        let token: Token =
+            serde_json::from_value(serde_json::json!({ "access_token": stored.access_token }))?;
+        Ok(Self {
+            token,
+            expires_at: stored.expires_at,
+        })
+    }
+
+    /// Whether the token is still valid with at least `skew` to spare.
+    fn is_fresh(&self, skew: Duration) -> bool {
+        Self::fresh_at(self.expires_at, Self::now(), skew)
+    }
+
+    fn fresh_at(expires_at: i64, now: i64, skew: Duration) -> bool {
+        expires_at - now > skew.as_secs() as i64
+    }
+
+    fn now() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// Classifies a failed token exchange so the retry loop can fail fast on
+/// deterministic errors while retrying transient ones.
+enum ExchangeError {
+    /// Worth retrying: a refused connection or a 5xx response.
+    Transient(Error),
+    /// Will recur on every attempt: a 4xx such as `invalid_grant`, or a
+    /// malformed response body.
+    Fatal(Error),
+}
+
+impl ExchangeError {
+    fn into_error(self) -> Error {
+        match self {
+            ExchangeError::Transient(err) | ExchangeError::Fatal(err) => err,
+        }
+    }
+}
+
 #[derive(Debug)]
-pub(crate) struct DefaultAuthorizedUser {
-    token: RwLock<Token>,
+pub struct DefaultAuthorizedUser {
+    tokens: RwLock<HashMap<ScopeKey, CachedToken>>,
+    storage: Arc<dyn TokenStorage>,
+    /// Credentials retained so refreshes never re-read the on-disk file, which
+    /// may not exist when built from a string or the interactive flow.
+    credentials: UserCredentials,
+    quota_project_id: Option<String>,
 }
 
 impl DefaultAuthorizedUser {
     const DEFAULT_TOKEN_GCP_URI: &'static str = "https://accounts.google.com/o/oauth2/token";
+    const DEFAULT_AUTH_GCP_URI: &'static str = "https://accounts.google.com/o/oauth2/auth";
     const USER_CREDENTIALS_PATH: &'static str =
         ".config/gcloud/application_default_credentials.json";
+    /// Public "installed application" client id/secret used by gcloud/gsutil
+    /// style CLIs. These are not secret — Google designates them for native
+    /// apps where no confidential secret can be kept.
+    const INSTALLED_CLIENT_ID: &'static str =
+        "764086051850-6qr4p6gpi6hn506pt8ejuq83di341hur.apps.googleusercontent.com";
+    const INSTALLED_CLIENT_SECRET: &'static str = "d-FL95Q19q7MQmFpd7hHD0Ty";
+    /// Stop serving a cached token once it is within this window of expiry,
+    /// forcing a refresh before the current token goes stale.
+    const EXPIRY_SKEW: Duration = Duration::from_secs(30);
+    /// Number of attempts made to acquire the initial token before giving up.
+    const MAX_RETRY_ATTEMPTS: u32 = 5;
+    /// Backoff applied after the first failed attempt; doubled on each retry.
+    const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+    /// Assumed lifetime when the token endpoint omits `expires_in`. Google
+    /// access tokens last an hour; the token is revalidated on the next
+    /// refresh regardless.
+    const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
 
-    pub(crate) async fn new(client: &HyperClient) -> Result<Self, Error> {
-        let token = RwLock::new(Self::get_token(client).await?);
-        Ok(Self { token })
+    pub fn builder() -> DefaultAuthorizedUserBuilder {
+        DefaultAuthorizedUserBuilder::new()
     }
 
-    async fn get_token(client: &Client) -> Result<Token, Error> {
-        log::debug!("Loading user credentials file");
+    pub(crate) async fn new(client: &Client) -> Result<Self, Error> {
+        Self::builder().build(client).await
+    }
+
+    /// Build from credentials held in memory as a JSON string, e.g. one read
+    /// from a secret manager into an environment variable, avoiding a staged
+    /// temp file.
+    pub async fn from_credentials_str(client: &Client, credentials: &str) -> Result<Self, Error> {
+        let cred = UserCredentials::from_str(credentials)?;
+        Self::with_credentials(client, cred, Self::default_storage()).await
+    }
+
+    fn default_storage() -> Arc<dyn TokenStorage> {
+        Arc::new(MemoryStorage::default())
+    }
+
+    async fn with_credentials(
+        client: &Client,
+        cred: UserCredentials,
+        storage: Arc<dyn TokenStorage>,
+    ) -> Result<Self, Error> {
+        let key = Self::scope_key(&[]);
+        let quota_project_id = cred.quota_project_id.clone();
+        let cached = Self::fetch_token_with_retry(client, &cred).await?;
+        storage.set(&key, cached.clone()).await;
+        let mut tokens = HashMap::new();
+        tokens.insert(key, cached);
+        Ok(Self {
+            tokens: RwLock::new(tokens),
+            storage,
+            credentials: cred,
+            quota_project_id,
+        })
+    }
+
+    /// Bootstrap a refresh token via the installed-application
+    /// authorization-code flow when no credentials exist yet.
+    ///
+    /// Prints a consent URL for the requested `scopes`, listens on a loopback
+    /// redirect for the returned authorization code, exchanges it for an
+    /// access + refresh token, and — when `persist` is set — writes the result
+    /// back to the credentials path in the same `UserCredentials` JSON shape.
+    /// Google only returns the refresh token on first consent, so persisting it
+    /// is the only way to avoid repeating the flow.
+    pub async fn authorize(
+        client: &Client,
+        scopes: &[&str],
+        persist: bool,
+    ) -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|err| Error::ServerUnavailable(err.to_string()))?;
+        let port = listener
+            .local_addr()
+            .map_err(|err| Error::ServerUnavailable(err.to_string()))?
+            .port();
+        let redirect_uri = format!("http://localhost:{port}");
+
+        let consent_url = Self::consent_url(scopes, &redirect_uri)?;
+        println!("Open the following URL in your browser to authorize access:\n\n{consent_url}\n");
+
+        let code = Self::wait_for_code(listener).await?;
+        let cred = Self::exchange_code(client, &code, &redirect_uri).await?;
+        if persist {
+            cred.persist(Self::credentials_path()?).await?;
+        }
+        Self::with_credentials(client, cred, Self::default_storage()).await
+    }
+
+    /// Build the Google consent URL for the installed-app client, requesting
+    /// offline access so a refresh token is returned.
+    fn consent_url(scopes: &[&str], redirect_uri: &str) -> Result<String, Error> {
+        let scope = scopes.join(" ");
+        let url = reqwest::Url::parse_with_params(
+            Self::DEFAULT_AUTH_GCP_URI,
+            &[
+                ("client_id", Self::INSTALLED_CLIENT_ID),
+                ("redirect_uri", redirect_uri),
+                ("response_type", "code"),
+                ("scope", scope.as_str()),
+                ("access_type", "offline"),
+                ("prompt", "consent"),
+            ],
+        )
+        .map_err(|err| Error::ServerUnavailable(err.to_string()))?;
+        Ok(url.into())
+    }
+
+    /// Accept a single loopback request and extract the `code` query parameter.
+    async fn wait_for_code(listener: TcpListener) -> Result<String, Error> {
+        let (mut stream, _) = listener
+            .accept()
+            .await
+            .map_err(|err| Error::ServerUnavailable(err.to_string()))?;
+
+        let mut buf = [0u8; 2048];
+        let read = stream
+            .read(&mut buf)
+            .await
+            .map_err(|err| Error::ServerUnavailable(err.to_string()))?;
+        let request = String::from_utf8_lossy(&buf[..read]);
+
+        let body = "You may close this window and return to the terminal.";
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+
+        let target = request
+            .split_whitespace()
+            .nth(1)
+            .ok_or_else(|| Error::ServerUnavailable("malformed redirect request".to_string()))?;
+        // Parse against a dummy base so `query_pairs` percent-decodes the code.
+        // Google sends it encoded (e.g. `4%2F0Ae...`); decoding here stops
+        // `exchange_code`'s form encoding from double-encoding the `%`.
+        let url = reqwest::Url::parse("http://localhost")
+            .and_then(|base| base.join(target))
+            .map_err(|err| Error::ServerUnavailable(err.to_string()))?;
+        url.query_pairs()
+            .find(|(key, _)| key == "code")
+            .map(|(_, code)| code.into_owned())
+            .ok_or_else(|| Error::ServerUnavailable("authorization code not received".to_string()))
+    }
+
+    /// Exchange an authorization code for access + refresh tokens.
+    async fn exchange_code(
+        client: &Client,
+        code: &str,
+        redirect_uri: &str,
+    ) -> Result<UserCredentials, Error> {
+        let response: AuthCodeResponse = client
+            .post(Self::DEFAULT_TOKEN_GCP_URI)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("client_id", Self::INSTALLED_CLIENT_ID),
+                ("client_secret", Self::INSTALLED_CLIENT_SECRET),
+            ])
+            .send()
+            .await
+            .map_err(Error::OAuthConnectionError)?
+            .error_for_status()
+            .map_err(|err| Error::ServerUnavailable(err.to_string()))?
+            .json()
+            .await?;
+        Ok(UserCredentials {
+            client_id: Self::INSTALLED_CLIENT_ID.to_string(),
+            client_secret: Self::INSTALLED_CLIENT_SECRET.to_string(),
+            refresh_token: response.refresh_token,
+            r#type: "authorized_user".to_string(),
+            quota_project_id: None,
+        })
+    }
+
+    /// Resolve the credentials file, honoring `GOOGLE_APPLICATION_CREDENTIALS`
+    /// and falling back to the gcloud default under the home directory.
+    fn credentials_path() -> Result<PathBuf, Error> {
+        if let Some(path) = std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+            return Ok(PathBuf::from(path));
+        }
         let mut home = dirs_next::home_dir().ok_or(Error::NoHomeDir)?;
         home.push(Self::USER_CREDENTIALS_PATH);
-        let cred = UserCredentials::from_file(home.display().to_string()).await?;
-        let token = client
+        Ok(home)
+    }
+
+    fn scope_key(scopes: &[&str]) -> ScopeKey {
+        let mut scopes = scopes.to_vec();
+        scopes.sort_unstable();
+        scopes.join(" ")
+    }
+
+    /// Acquire the initial token, retrying transient failures with exponential
+    /// backoff.
+    ///
+    /// Fresh container workloads (GKE/k8s pods) routinely come up before the
+    /// credential endpoint is reachable, so a single attempt flakes the whole
+    /// process. Connection and server errors are retried; deterministic errors
+    /// such as a missing credentials file or malformed JSON fail fast.
+    async fn fetch_token_with_retry(
+        client: &Client,
+        cred: &UserCredentials,
+    ) -> Result<CachedToken, Error> {
+        let mut delay = Self::INITIAL_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            match Self::exchange(client, cred).await {
+                Ok(cached) => return Ok(cached),
+                Err(ExchangeError::Transient(err)) if attempt < Self::MAX_RETRY_ATTEMPTS => {
+                    log::warn!("token acquisition attempt {attempt} failed: {err}; retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err.into_error()),
+            }
+        }
+    }
+
+    async fn exchange(
+        client: &Client,
+        cred: &UserCredentials,
+    ) -> Result<CachedToken, ExchangeError> {
+        let response = client
             .post(Self::DEFAULT_TOKEN_GCP_URI)
             .header("content-type", "application/json")
             .json(&RefreshRequest {
-                client_id: cred.client_id,
-                client_secret: cred.client_secret,
+                client_id: cred.client_id.clone(),
+                client_secret: cred.client_secret.clone(),
                 grant_type: "refresh_token".to_string(),
-                refresh_token: cred.refresh_token,
+                refresh_token: cred.refresh_token.clone(),
             })
             .send()
             .await
-            .map_err(Error::OAuthConnectionError)?
-            .error_for_status()
-            .map_err(|err| Error::ServerUnavailable(err.to_string()))?
+            .map_err(|err| ExchangeError::Transient(Error::OAuthConnectionError(err)))?;
+
+        // 4xx (e.g. `invalid_grant` from a revoked refresh token) is
+        // deterministic and must fail fast; 5xx is worth retrying.
+        let status = response.status();
+        let response = response.error_for_status().map_err(|err| {
+            let err = Error::ServerUnavailable(err.to_string());
+            if Self::status_is_transient(status) {
+                ExchangeError::Transient(err)
+            } else {
+                ExchangeError::Fatal(err)
+            }
+        })?;
+
+        let response: TokenResponse = response
             .json()
-            .await?;
-        Ok(token)
+            .await
+            .map_err(|err| ExchangeError::Fatal(Error::OAuthConnectionError(err)))?;
+        let lifetime = response
+            .expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(Self::DEFAULT_TOKEN_LIFETIME);
+        Ok(CachedToken::new(response.token, lifetime))
+    }
+
+    /// Whether an HTTP status from the token endpoint is worth retrying: 5xx
+    /// is transient, 4xx (bad/revoked credentials) is deterministic.
+    fn status_is_transient(status: reqwest::StatusCode) -> bool {
+        status.is_server_error()
     }
 }
 
 #[async_trait]
 impl ServiceAccount for DefaultAuthorizedUser {
-    async fn project_id(&self, _: &HyperClient) -> Result<String, Error> {
+    async fn project_id(&self, _: &Client) -> Result<String, Error> {
+        if let Some(project) = &self.quota_project_id {
+            return Ok(project.clone());
+        }
+        for var in ["GOOGLE_CLOUD_PROJECT", "GCP_PROJECT"] {
+            if let Ok(project) = std::env::var(var) {
+                if !project.is_empty() {
+                    return Ok(project);
+                }
+            }
+        }
         Err(Error::NoProjectId)
     }
 
-    fn get_token(&self, _scopes: &[&str]) -> Option<Token> {
-        Some(self.token.read().unwrap().clone())
+    fn get_token(&self, scopes: &[&str]) -> Option<Token> {
+        let key = Self::scope_key(scopes);
+        let tokens = self.tokens.read().unwrap();
+        // Fall back to the token fetched at construction time, which is cached
+        // under the empty scope key: the user-credentials refresh grant ignores
+        // the requested scopes, so it is valid for any scope set.
+        let cached = tokens.get(&key).or_else(|| tokens.get(""))?;
+        cached
+            .is_fresh(Self::EXPIRY_SKEW)
+            .then(|| cached.token.clone())
     }
 
-    async fn refresh_token(&self, client: &Client, _scopes: &[&str]) -> Result<Token, Error> {
-        let token = Self::get_token(client).await?;
-        *self.token.write().unwrap() = token.clone();
+    async fn refresh_token(&self, client: &Client, scopes: &[&str]) -> Result<Token, Error> {
+        let key = Self::scope_key(scopes);
+        let cached = Self::exchange(client, &self.credentials)
+            .await
+            .map_err(ExchangeError::into_error)?;
+        let token = cached.token.clone();
+        self.storage.set(&key, cached.clone()).await;
+        self.tokens.write().unwrap().insert(key, cached);
         Ok(token)
     }
 }
 
+/// Builder for [`DefaultAuthorizedUser`], used to inject a custom
+/// [`TokenStorage`] backend before acquiring the first token.
+pub struct DefaultAuthorizedUserBuilder {
+    storage: Arc<dyn TokenStorage>,
+}
+
+impl DefaultAuthorizedUserBuilder {
+    fn new() -> Self {
+        Self {
+            storage: DefaultAuthorizedUser::default_storage(),
+        }
+    }
+
+    /// Use the given storage backend (e.g. Redis, sqlite, a file) instead of
+    /// the default in-memory store.
+    pub fn with_storage(mut self, storage: Arc<dyn TokenStorage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub async fn build(self, client: &Client) -> Result<DefaultAuthorizedUser, Error> {
+        let cred = UserCredentials::from_file(DefaultAuthorizedUser::credentials_path()?).await?;
+        let restored = self.storage.all().await;
+        if restored.is_empty() {
+            return DefaultAuthorizedUser::with_credentials(client, cred, self.storage).await;
+        }
+        log::debug!("Restored {} token(s) from storage", restored.len());
+        Ok(DefaultAuthorizedUser {
+            tokens: RwLock::new(restored),
+            storage: self.storage,
+            quota_project_id: cred.quota_project_id.clone(),
+            credentials: cred,
+        })
+    }
+}
+
+/// Pluggable persistence for refreshed tokens so they survive process
+/// restarts. Keyed by the same scope string used by the in-memory cache.
+#[async_trait]
+pub trait TokenStorage: Send + Sync + std::fmt::Debug {
+    /// Return the stored token for the scope key, if one is present.
+    async fn get(&self, scope_key: &str) -> Option<CachedToken>;
+    /// Store a token, with its expiry, under the scope key.
+    async fn set(&self, scope_key: &str, token: CachedToken);
+    /// Return every stored token, keyed by scope, to seed the cache on startup.
+    async fn all(&self) -> HashMap<String, CachedToken>;
+}
+
+/// In-memory [`TokenStorage`], the default backend. Does not outlive the
+/// process.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    tokens: RwLock<HashMap<String, CachedToken>>,
+}
+
+#[async_trait]
+impl TokenStorage for MemoryStorage {
+    async fn get(&self, scope_key: &str) -> Option<CachedToken> {
+        self.tokens.read().unwrap().get(scope_key).cloned()
+    }
+
+    async fn set(&self, scope_key: &str, token: CachedToken) {
+        self.tokens
+            .write()
+            .unwrap()
+            .insert(scope_key.to_string(), token);
+    }
+
+    async fn all(&self) -> HashMap<String, CachedToken> {
+        self.tokens.read().unwrap().clone()
+    }
+}
+
+/// File-backed [`TokenStorage`] that serializes the scope-keyed tokens to a
+/// JSON file. Best-effort: I/O and parse errors are logged and treated as a
+/// cache miss so a failed read never blocks a refresh.
+#[derive(Debug)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    async fn load(&self) -> HashMap<String, StoredToken> {
+        match fs::read_to_string(&self.path).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl TokenStorage for FileStorage {
+    async fn get(&self, scope_key: &str) -> Option<CachedToken> {
+        let stored = self.load().await.remove(scope_key)?;
+        CachedToken::from_stored(stored).ok()
+    }
+
+    async fn set(&self, scope_key: &str, token: CachedToken) {
+        let mut tokens = self.load().await;
+        tokens.insert(scope_key.to_string(), token.to_stored());
+        match serde_json::to_string(&tokens) {
+            Ok(content) => {
+                if let Err(err) = fs::write(&self.path, content).await {
+                    log::warn!("failed to persist token to {}: {err}", self.path.display());
+                }
+            }
+            Err(err) => log::warn!("failed to serialize tokens for storage: {err}"),
+        }
+    }
+
+    async fn all(&self) -> HashMap<String, CachedToken> {
+        self.load()
+            .await
+            .into_iter()
+            .filter_map(|(key, stored)| Some((key, CachedToken::from_stored(stored).ok()?)))
+            .collect()
+    }
+}
+
+/// On-disk representation of a [`CachedToken`] for [`FileStorage`]. Holds only
+/// the bearer string and absolute expiry so `Token` need not be `Serialize`.
+#[derive(Serialize, Deserialize, Debug)]
+struct StoredToken {
+    access_token: String,
+    expires_at: i64,
+}
+
 #[derive(Serialize, Debug)]
 struct RefreshRequest {
     client_id: String,
@@ -75,6 +554,23 @@ struct RefreshRequest {
     refresh_token: String,
 }
 
+/// OAuth token endpoint response.
+///
+/// The token fields are flattened into [`Token`]; `expires_in` is pulled out
+/// separately so the absolute expiry can be computed and stored.
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    #[serde(flatten)]
+    token: Token,
+    expires_in: Option<u64>,
+}
+
+/// Token endpoint response for the authorization-code grant.
+#[derive(Deserialize, Debug)]
+struct AuthCodeResponse {
+    refresh_token: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct UserCredentials {
     /// Client id
@@ -85,13 +581,111 @@ struct UserCredentials {
     pub(crate) refresh_token: String,
     /// Type
     pub(crate) r#type: String,
+    /// Project to bill and attribute API calls to, when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) quota_project_id: Option<String>,
 }
 
 impl UserCredentials {
     async fn from_file<T: AsRef<Path>>(path: T) -> Result<UserCredentials, Error> {
+        log::debug!("Loading user credentials file");
         let content = fs::read_to_string(path)
             .await
             .map_err(Error::UserProfilePath)?;
-        Ok(serde_json::from_str(&content).map_err(Error::UserProfileFormat)?)
+        Self::from_str(&content)
+    }
+
+    fn from_str(content: &str) -> Result<UserCredentials, Error> {
+        serde_json::from_str(content).map_err(Error::UserProfileFormat)
+    }
+
+    async fn persist<T: AsRef<Path>>(&self, path: T) -> Result<(), Error> {
+        let content = serde_json::to_string_pretty(self).map_err(Error::UserProfileFormat)?;
+        fs::write(path, content).await.map_err(Error::UserProfilePath)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_key_is_order_independent() {
+        assert_eq!(
+            DefaultAuthorizedUser::scope_key(&["b", "a"]),
+            DefaultAuthorizedUser::scope_key(&["a", "b"]),
+        );
+        assert_eq!(DefaultAuthorizedUser::scope_key(&["a", "b"]), "a b");
+        assert_eq!(DefaultAuthorizedUser::scope_key(&[]), "");
+    }
+
+    #[test]
+    fn freshness_honors_skew() {
+        let skew = Duration::from_secs(30);
+        // Comfortably ahead of the skew window: fresh.
+        assert!(CachedToken::fresh_at(1_060, 1_000, skew));
+        // Exactly at the skew boundary and inside it: stale.
+        assert!(!CachedToken::fresh_at(1_030, 1_000, skew));
+        assert!(!CachedToken::fresh_at(1_020, 1_000, skew));
+        // Already expired: stale.
+        assert!(!CachedToken::fresh_at(900, 1_000, skew));
+    }
+
+    #[test]
+    fn only_server_errors_are_transient() {
+        use reqwest::StatusCode;
+        assert!(DefaultAuthorizedUser::status_is_transient(
+            StatusCode::INTERNAL_SERVER_ERROR
+        ));
+        assert!(DefaultAuthorizedUser::status_is_transient(
+            StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert!(!DefaultAuthorizedUser::status_is_transient(
+            StatusCode::BAD_REQUEST
+        ));
+        assert!(!DefaultAuthorizedUser::status_is_transient(
+            StatusCode::UNAUTHORIZED
+        ));
+        assert!(!DefaultAuthorizedUser::status_is_transient(
+            StatusCode::FORBIDDEN
+        ));
+    }
+
+    #[test]
+    fn credentials_path_prefers_env() {
+        std::env::set_var("GOOGLE_APPLICATION_CREDENTIALS", "/tmp/creds.json");
+        let path = DefaultAuthorizedUser::credentials_path().unwrap();
+        std::env::remove_var("GOOGLE_APPLICATION_CREDENTIALS");
+        assert_eq!(path, PathBuf::from("/tmp/creds.json"));
+    }
+
+    fn user_with_quota(quota: Option<String>) -> DefaultAuthorizedUser {
+        DefaultAuthorizedUser {
+            tokens: RwLock::new(HashMap::new()),
+            storage: DefaultAuthorizedUser::default_storage(),
+            credentials: UserCredentials {
+                client_id: String::new(),
+                client_secret: String::new(),
+                refresh_token: String::new(),
+                r#type: "authorized_user".to_string(),
+                quota_project_id: quota.clone(),
+            },
+            quota_project_id: quota,
+        }
+    }
+
+    #[tokio::test]
+    async fn project_id_prefers_quota_then_env() {
+        let client = Client::new();
+
+        // An explicit quota project takes precedence.
+        let user = user_with_quota(Some("quota-proj".to_string()));
+        assert_eq!(user.project_id(&client).await.unwrap(), "quota-proj");
+
+        // Otherwise fall back to the environment.
+        std::env::set_var("GOOGLE_CLOUD_PROJECT", "env-proj");
+        let user = user_with_quota(None);
+        assert_eq!(user.project_id(&client).await.unwrap(), "env-proj");
+        std::env::remove_var("GOOGLE_CLOUD_PROJECT");
     }
 }